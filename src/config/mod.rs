@@ -7,4 +7,4 @@ static CONFIG: OnceLock<String> = OnceLock::new();
 
 pub struct RefreshingConfigFile {
     path: PathBuf,
-}
\ No newline at end of file
+}
@@ -0,0 +1,72 @@
+use std::{marker::PhantomData, sync::Arc};
+
+use color_eyre::Result;
+use serde::{de::DeserializeOwned, Serialize};
+
+use super::{
+    config_file::ConfigurationFile, file_handler::FileHander, format::ConfigFormat,
+    migration::Migration,
+};
+
+pub(super) type Validator<T> = Arc<dyn Fn(&T) -> Result<(), String> + Send + Sync>;
+pub(super) type DynFormat = Arc<dyn ConfigFormat + Send + Sync>;
+
+/// Incrementally configures a [`ConfigurationFile`] before opening it.
+///
+/// Obtained via [`ConfigurationFile::builder`].
+pub struct ConfigurationFileBuilder<'f, T> {
+    file: &'f FileHander,
+    format: DynFormat,
+    migrations: Vec<Migration>,
+    validator: Option<Validator<T>>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<'f, T> ConfigurationFileBuilder<'f, T> {
+    pub(super) fn new(file: &'f FileHander) -> Self {
+        Self {
+            file,
+            format: Arc::new(file.format()),
+            migrations: Vec::new(),
+            validator: None,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Overrides the format auto-detected from the file's extension. Takes
+    /// any [`ConfigFormat`], not just the built-in [`super::Format`]
+    /// variants, so callers can plug in their own encoding.
+    pub fn format(mut self, format: impl ConfigFormat + Send + Sync + 'static) -> Self {
+        self.format = Arc::new(format);
+        self
+    }
+
+    /// Registers an ordered list of schema migrations, applied in sequence
+    /// to bring an older on-disk document up to the newest version any
+    /// step in `migrations` upgrades to.
+    pub fn migrations(mut self, migrations: Vec<Migration>) -> Self {
+        self.migrations = migrations;
+        self
+    }
+
+    /// Registers a semantic validation hook, run after a document parses
+    /// successfully. A failure turns the result into
+    /// [`super::ConfigResult::Invalid`] while keeping the previous
+    /// last-known-good value, instead of accepting the new (but nonsensical)
+    /// one.
+    pub fn validator(
+        mut self,
+        validator: impl Fn(&T) -> Result<(), String> + Send + Sync + 'static,
+    ) -> Self {
+        self.validator = Some(Arc::new(validator));
+        self
+    }
+}
+
+impl<'f, T: Default + Serialize + DeserializeOwned + Send + Sync + 'static>
+    ConfigurationFileBuilder<'f, T>
+{
+    pub fn build(self) -> Result<ConfigurationFile<T>> {
+        ConfigurationFile::from_builder(self.file, self.format, self.migrations, self.validator)
+    }
+}
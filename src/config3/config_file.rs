@@ -1,37 +1,53 @@
-use std::{
-    fmt::Display,
-    mem,
-    sync::atomic::{AtomicBool, Ordering},
-};
+use std::{fmt::Display, mem};
 
 use color_eyre::Result;
 use serde::{de::DeserializeOwned, Serialize};
 use tokio::{sync::watch, task::JoinHandle};
 
-use super::file_handler::FileHander;
+use super::{
+    builder::{ConfigurationFileBuilder, DynFormat, Validator},
+    file_handler::FileHander,
+    format::ConfigFormat,
+    migration::{apply_migrations, Migration, MigrationError, MigrationOutcome},
+};
 
 type ConfigError = String;
 #[derive(Debug, Clone)]
 pub enum ConfigResult<T> {
     Valid(T),
     Invalid(T, ConfigError),
+    /// The document's `version` field is newer than any registered
+    /// migration knows about. Carries the last-known-good value so
+    /// consumers keep operating on sane data.
+    UnsupportedVersion(T, u32),
 }
 
 impl<T: Default> ConfigResult<T> {
     fn with_error(&mut self, errors: &impl Display) {
+        let v = self.take();
+        *self = Self::Invalid(v, errors.to_string());
+    }
+
+    fn with_unsupported_version(&mut self, version: u32) {
+        let v = self.take();
+        *self = Self::UnsupportedVersion(v, version);
+    }
+
+    fn take(&mut self) -> T {
         let v = match self {
             ConfigResult::Valid(v) => v,
             ConfigResult::Invalid(v, _) => v,
+            ConfigResult::UnsupportedVersion(v, _) => v,
         };
-        let v = mem::take(v);
 
-        *self = Self::Invalid(v, errors.to_string());
+        mem::take(v)
     }
 
     fn get(&self) -> &T {
         match self {
             ConfigResult::Valid(ref v) => v,
             ConfigResult::Invalid(ref v, _) => v,
+            ConfigResult::UnsupportedVersion(ref v, _) => v,
         }
     }
 
@@ -39,6 +55,7 @@ impl<T: Default> ConfigResult<T> {
         match self {
             ConfigResult::Valid(v) => v,
             ConfigResult::Invalid(v, _) => v,
+            ConfigResult::UnsupportedVersion(v, _) => v,
         }
     }
 }
@@ -62,45 +79,147 @@ pub struct ConfigurationFile<T> {
     tx: watch::Sender<ConfigResult<T>>,
 }
 
-pub(super) static HAS_DESERIALIZED: AtomicBool = AtomicBool::new(true);
+/// Parses `contents` with `format`, runs it through `migrations`, and
+/// deserializes the (possibly migrated) document into `T`.
+///
+/// Returns the parsed value alongside the migrated document re-rendered in
+/// `format`, if a migration actually ran, so the caller can persist the
+/// upgrade back to disk.
+fn parse_and_migrate<T: Default + DeserializeOwned>(
+    contents: &str,
+    format: &dyn ConfigFormat,
+    migrations: &[Migration],
+) -> (ConfigResult<T>, Option<String>) {
+    let raw: serde_json::Value = match format.parse(contents) {
+        Ok(raw) => raw,
+        Err(err) => return (ConfigResult::Invalid(T::default(), err), None),
+    };
+
+    let (value, rendered) = match apply_migrations(raw, migrations) {
+        Ok(MigrationOutcome::UpToDate(value)) => (value, None),
+        Ok(MigrationOutcome::Migrated(value)) => match format.render(&value) {
+            Ok(rendered) => (value, Some(rendered)),
+            Err(err) => return (ConfigResult::Invalid(T::default(), err), None),
+        },
+        Err(MigrationError::UnsupportedVersion(version)) => {
+            return (
+                ConfigResult::UnsupportedVersion(T::default(), version),
+                None,
+            )
+        }
+        Err(MigrationError::Failed(err)) => {
+            return (ConfigResult::Invalid(T::default(), err), None)
+        }
+    };
+
+    match serde_json::from_value::<T>(value) {
+        Ok(config) => (ConfigResult::Valid(config), rendered),
+        Err(err) => (ConfigResult::Invalid(T::default(), err.to_string()), None),
+    }
+}
 
 impl<T: Default + Serialize + DeserializeOwned + Send + Sync + 'static> ConfigurationFile<T> {
+    /// Opens `file`, using the format it auto-detected from its extension
+    /// and no migrations.
     pub fn new(file: &FileHander) -> Result<Self> {
+        Self::builder(file).build()
+    }
+
+    /// Opens `file`, overriding its auto-detected format. Takes any
+    /// [`ConfigFormat`], not just the built-in [`super::Format`] variants.
+    pub fn with_format(
+        file: &FileHander,
+        format: impl ConfigFormat + Send + Sync + 'static,
+    ) -> Result<Self> {
+        Self::builder(file).format(format).build()
+    }
+
+    /// Starts building a [`ConfigurationFile`] with a non-default format
+    /// and/or a migration pipeline.
+    pub fn builder(file: &FileHander) -> ConfigurationFileBuilder<'_, T> {
+        ConfigurationFileBuilder::new(file)
+    }
+
+    pub(super) fn from_builder(
+        file: &FileHander,
+        format: DynFormat,
+        migrations: Vec<Migration>,
+        validator: Option<Validator<T>>,
+    ) -> Result<Self> {
         let (raw_tx, mut raw_rx) = (file.tx(), file.rx());
 
-        let initial_value = match serde_json::from_str::<T>(&std::fs::read_to_string(file.path())?)
-        {
-            Ok(v) => ConfigResult::Valid(v),
-            Err(err) => ConfigResult::Invalid(T::default(), err.to_string()),
-        };
+        let (mut initial_value, initial_rendered) = parse_and_migrate::<T>(
+            &std::fs::read_to_string(file.path())?,
+            format.as_ref(),
+            &migrations,
+        );
+
+        if let ConfigResult::Valid(ref value) = initial_value {
+            if let Some(err) = validator.as_ref().and_then(|v| v(value).err()) {
+                initial_value = ConfigResult::Invalid(T::default(), err);
+            }
+        }
+
+        if let Some(rendered) = initial_rendered {
+            raw_tx.send_if_modified(|current| {
+                let changed = current != &rendered;
+                if changed {
+                    *current = rendered;
+                }
+
+                changed
+            });
+        }
 
         let (config_tx, mut config_rx) = watch::channel(initial_value);
 
         config_rx.mark_changed();
 
         let config_tx2 = config_tx.clone();
+        let raw_tx2 = raw_tx.clone();
+        let migrations2 = migrations.clone();
+        let validator2 = validator.clone();
+        let format2 = format.clone();
         let deserializer_thread = tokio::spawn(async move {
             let config_tx = config_tx2;
+            let raw_tx = raw_tx2;
+            let migrations = migrations2;
+            let validator = validator2;
+            let format = format2;
             while let Ok(()) = raw_rx.changed().await {
-                let contents = raw_rx.borrow();
-                let parsed = serde_json::from_str::<T>(&contents);
-                HAS_DESERIALIZED.store(true, Ordering::SeqCst);
+                let contents = raw_rx.borrow().clone();
+                let (config, rendered) =
+                    parse_and_migrate::<T>(&contents, format.as_ref(), &migrations);
 
-                let config = match parsed {
-                    Err(err) => {
-                        println!("Error while parsing config: {err}");
+                if let Some(rendered) = rendered {
+                    raw_tx.send_if_modified(|current| {
+                        let changed = current != &rendered;
+                        if changed {
+                            *current = rendered;
+                        }
 
-                        config_tx.send_modify(|current| {
-                            println!("Send from deserializer (error)!");
-                            current.with_error(&err);
-                        });
+                        changed
+                    });
+                }
 
+                if let ConfigResult::Valid(ref value) = config {
+                    if let Some(err) = validator.as_ref().and_then(|v| v(value).err()) {
+                        println!("Config failed validation: {err}");
+                        config_tx.send_modify(|current| current.with_error(&err));
                         continue;
                     }
-                    Ok(config) => config,
-                };
+                }
 
-                config_tx.send_replace(config.into());
+                match config {
+                    ConfigResult::Invalid(_, ref err) => {
+                        println!("Error while parsing config: {err}");
+                        config_tx.send_modify(|current| current.with_error(err));
+                    }
+                    ConfigResult::UnsupportedVersion(_, version) => {
+                        config_tx.send_modify(|current| current.with_unsupported_version(version));
+                    }
+                    ConfigResult::Valid(_) => config_tx.send_replace(config),
+                }
             }
         });
 
@@ -108,13 +227,22 @@ impl<T: Default + Serialize + DeserializeOwned + Send + Sync + 'static> Configur
         let serializer_thread = tokio::spawn(async move {
             let mut config_rx = config_rx2;
             while let Ok(()) = config_rx.changed().await {
-                if HAS_DESERIALIZED.load(Ordering::SeqCst) {
-                    HAS_DESERIALIZED.store(false, Ordering::SeqCst);
-                    continue;
-                }
+                // `format.render` can fail even for a `Valid` config -- e.g.
+                // TOML requires a map/struct at the document root, which not
+                // every `T` is -- so this logs and skips the write instead
+                // of panicking the serializer task.
+                let rendered = serde_json::to_value(config_rx.borrow().get())
+                    .map_err(|err| err.to_string())
+                    .and_then(|value| format.render(&value));
+
+                let config = match rendered {
+                    Ok(config) => config,
+                    Err(err) => {
+                        eprintln!("Failed to render config for writing: {err}");
+                        continue;
+                    }
+                };
 
-                let config = serde_json::to_string(config_rx.borrow().get())
-                    .expect("Internal config object should always be valid.");
                 raw_tx.send_if_modified(|current| {
                     let changed = current != &config;
                     if changed {
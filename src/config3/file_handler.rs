@@ -1,33 +1,52 @@
 use std::{
+    io::Write,
     path::{Path, PathBuf},
-    sync::atomic::{AtomicBool, Ordering},
+    sync::{Arc, Mutex},
+    time::Duration,
 };
 
 use color_eyre::Result;
 use notify::{
-    event::{AccessKind, AccessMode},
+    event::{AccessKind, AccessMode, ModifyKind},
     Event, EventKind, INotifyWatcher, RecursiveMode, Watcher,
 };
-use tokio::{sync::watch, task::JoinHandle};
+use tokio::{
+    sync::{mpsc, watch},
+    task::JoinHandle,
+};
 
-use crate::config3::HAS_DESERIALIZED;
+use crate::config3::format::Format;
 
 pub struct FileHander {
     path: PathBuf,
     watcher: INotifyWatcher,
+    debouncer: JoinHandle<()>,
     saver: JoinHandle<()>,
     rx: watch::Receiver<String>,
     tx: watch::Sender<String>,
+    format: Format,
 }
 
-static HAS_WRITTEN: AtomicBool = AtomicBool::new(false);
-
 impl FileHander {
-    fn file_watcher(path: &Path, tx: watch::Sender<String>) -> Result<INotifyWatcher> {
+    /// Watches `path`'s parent directory rather than `path` itself, so the
+    /// watch survives the `rename`-over-target used by [`Self::file_saver`]
+    /// to write atomically (a direct watch on `path` would be left looking
+    /// at a now-unlinked inode after such a rename), and so renames from
+    /// external tools (editors that save atomically, `mv`, ...) are seen
+    /// too.
+    ///
+    /// Does no I/O itself: every write-shaped event just pokes
+    /// `touch_tx`, leaving [`Self::debounce_task`] to decide when the file
+    /// has actually settled.
+    fn file_watcher(path: &Path, touch_tx: mpsc::UnboundedSender<()>) -> Result<INotifyWatcher> {
         let path_inner = path.to_owned();
-        // Watch file when read..
+        let parent = path
+            .parent()
+            .map(Path::to_owned)
+            .unwrap_or_else(|| PathBuf::from("."));
+
         let mut watcher = notify::recommended_watcher(move |res| {
-            let ev = match res {
+            let ev: Event = match res {
                 Ok(ev) => ev,
                 Err(err) => {
                     eprintln!("{err:?}");
@@ -35,20 +54,59 @@ impl FileHander {
                 }
             };
 
-            // If this was written to.
-            if matches!(
-                ev,
-                Event {
-                    kind: EventKind::Access(AccessKind::Close(AccessMode::Write)),
-                    ..
-                }
-            ) {
-                if HAS_WRITTEN.load(Ordering::SeqCst) {
-                    HAS_WRITTEN.store(false, Ordering::SeqCst);
-                    return;
+            if !ev.paths.iter().any(|p| p == &path_inner) {
+                return;
+            }
+
+            // Direct writes end in a `Close(Write)`; atomic writes replace
+            // the file via `rename`, which inotify reports as a
+            // `Modify(Name(_))` on the directory watch above (the target
+            // path shows up in `ev.paths` of the `To` half and of the
+            // combined `Both` event; the `From` half only names the source).
+            let is_write = matches!(
+                ev.kind,
+                EventKind::Access(AccessKind::Close(AccessMode::Write))
+                    | EventKind::Modify(ModifyKind::Name(_))
+            );
+
+            if is_write {
+                let _ = touch_tx.send(());
+            }
+        })?;
+
+        watcher.watch(&parent, RecursiveMode::NonRecursive)?;
+
+        Ok(watcher)
+    }
+
+    /// Coalesces a burst of `touch_tx` pokes into a single reload: each poke
+    /// (re)arms a `window`-long timer, and the file is only read back once
+    /// it has been quiet for the whole window. Pokes arrive for both direct
+    /// writes and atomic rename-over-target saves (see
+    /// [`Self::file_watcher`]), so this covers editors that save via a
+    /// burst of truncate/write/rename steps too.
+    ///
+    /// `last_written` is compared against the freshly-read contents to
+    /// ignore the echo of our own writes, rather than the racy,
+    /// process-global flag this used to rely on.
+    fn debounce_task(
+        path: PathBuf,
+        mut touch_rx: mpsc::UnboundedReceiver<()>,
+        tx: watch::Sender<String>,
+        last_written: Arc<Mutex<Option<String>>>,
+        window: Duration,
+    ) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            while touch_rx.recv().await.is_some() {
+                while let Ok(Some(())) = tokio::time::timeout(window, touch_rx.recv()).await {
+                    // Still hearing from it -- keep the timer reset.
                 }
 
-                if let Ok(contents) = std::fs::read_to_string(&path_inner) {
+                if let Ok(contents) = std::fs::read_to_string(&path) {
+                    if last_written.lock().unwrap().as_deref() == Some(contents.as_str()) {
+                        continue;
+                    }
+
                     tx.send_if_modified(|s| {
                         let modified = s != &contents;
                         if modified {
@@ -59,41 +117,86 @@ impl FileHander {
                     });
                 }
             }
-        })?;
-
-        watcher.watch(path, RecursiveMode::NonRecursive)?;
-
-        Ok(watcher)
+        })
     }
 
-    fn file_saver(path: &Path, mut rx: watch::Receiver<String>) -> JoinHandle<()> {
+    /// Writes each new value atomically: serialize into a temp file next to
+    /// `path`, `sync_all` it, then `rename` it over `path`, so a crash
+    /// mid-write never leaves a half-written config on disk.
+    fn file_saver(
+        path: &Path,
+        mut rx: watch::Receiver<String>,
+        last_written: Arc<Mutex<Option<String>>>,
+    ) -> JoinHandle<()> {
         let path = path.to_owned();
+        let tmp_path = Self::temp_path(&path);
+
         tokio::spawn(async move {
             while let Ok(()) = rx.changed().await {
-                let contents = rx.borrow();
+                let contents = rx.borrow().clone();
 
-                if HAS_WRITTEN.load(Ordering::SeqCst) && HAS_DESERIALIZED.load(Ordering::SeqCst) {
-                    continue;
-                }
+                *last_written.lock().unwrap() = Some(contents.clone());
+
+                let result = (|| -> std::io::Result<()> {
+                    let mut tmp_file = std::fs::File::create(&tmp_path)?;
+                    tmp_file.write_all(contents.as_bytes())?;
+                    tmp_file.sync_all()?;
+                    std::fs::rename(&tmp_path, &path)
+                })();
 
-                println!("Written to file!");
-                HAS_WRITTEN.store(true, Ordering::SeqCst);
-                std::fs::write(&path, contents.as_bytes()).unwrap();
+                if let Err(err) = result {
+                    eprintln!("Failed to write config to {}: {err}", path.display());
+                } else {
+                    println!("Written to file!");
+                }
             }
         })
     }
 
+    fn temp_path(path: &Path) -> PathBuf {
+        let file_name = path
+            .file_name()
+            .map(|name| format!(".{}.tmp", name.to_string_lossy()))
+            .unwrap_or_else(|| ".config.tmp".to_string());
+
+        path.with_file_name(file_name)
+    }
+
+    /// The default quiet period [`Self::new`] waits for before reloading a
+    /// changed file.
+    const DEFAULT_DEBOUNCE: Duration = Duration::from_millis(250);
+
     pub fn new(path: impl AsRef<Path>) -> Result<Self> {
+        Self::with_debounce(path, Self::DEFAULT_DEBOUNCE)
+    }
+
+    /// Like [`Self::new`], but with a custom debounce window for coalescing
+    /// bursts of filesystem events into a single reload.
+    pub fn with_debounce(path: impl AsRef<Path>, debounce: Duration) -> Result<Self> {
         let path = path.as_ref().to_path_buf();
+        let format = Format::from_extension(&path);
         let (tx, rx) = watch::channel(std::fs::read_to_string(&path)?);
-        let watcher = Self::file_watcher(&path, tx.clone())?;
-        let saver = Self::file_saver(&path, rx.clone());
+        let last_written = Arc::new(Mutex::new(None));
+
+        let (touch_tx, touch_rx) = mpsc::unbounded_channel();
+        let watcher = Self::file_watcher(&path, touch_tx)?;
+        let debouncer = Self::debounce_task(
+            path.clone(),
+            touch_rx,
+            tx.clone(),
+            last_written.clone(),
+            debounce,
+        );
+        let saver = Self::file_saver(&path, rx.clone(), last_written);
+
         Ok(Self {
             path,
             watcher,
+            debouncer,
             saver,
             rx,
             tx,
+            format,
         })
     }
 
@@ -101,9 +204,17 @@ impl FileHander {
         &self.path
     }
 
+    /// The format auto-detected from this file's extension, falling back to
+    /// JSON. Used as the default by [`super::ConfigurationFile::new`].
+    pub(super) fn format(&self) -> Format {
+        self.format
+    }
+
     pub fn stop(mut self) -> Result<()> {
+        self.debouncer.abort();
         self.saver.abort();
-        self.watcher.unwatch(&self.path)?;
+        self.watcher
+            .unwatch(self.path.parent().unwrap_or(&self.path))?;
 
         Ok(())
     }
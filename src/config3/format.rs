@@ -0,0 +1,64 @@
+use std::path::Path;
+
+use serde_json::Value;
+
+pub type FormatError = String;
+
+/// A pluggable (de)serialization format for configuration files.
+///
+/// Implementations translate between the on-disk text representation and a
+/// [`serde_json::Value`] -- the common representation [`super::migration`]
+/// and [`super::ConfigurationFile`] operate on internally -- rather than a
+/// generic `T`, so the trait stays object-safe and a caller can plug in
+/// their own format via [`super::ConfigurationFileBuilder::format`] instead
+/// of being limited to the built-in [`Format`] variants.
+pub trait ConfigFormat {
+    fn parse(&self, source: &str) -> Result<Value, FormatError>;
+    fn render(&self, value: &Value) -> Result<String, FormatError>;
+}
+
+/// The built-in formats this crate ships with.
+///
+/// Picked automatically from a file's extension in [`super::FileHander::new`],
+/// or chosen explicitly via [`super::ConfigurationFile::with_format`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Format {
+    #[default]
+    Json,
+    Toml,
+    Yaml,
+}
+
+impl Format {
+    /// Guesses a format from a file's extension, falling back to JSON.
+    pub fn from_extension(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => Format::Toml,
+            Some("yaml" | "yml") => Format::Yaml,
+            _ => Format::Json,
+        }
+    }
+}
+
+impl ConfigFormat for Format {
+    fn parse(&self, source: &str) -> Result<Value, FormatError> {
+        match self {
+            Format::Json => serde_json::from_str(source).map_err(|err| err.to_string()),
+            Format::Toml => toml::from_str(source).map_err(|err| err.to_string()),
+            Format::Yaml => serde_yaml::from_str(source).map_err(|err| err.to_string()),
+        }
+    }
+
+    fn render(&self, value: &Value) -> Result<String, FormatError> {
+        match self {
+            Format::Json => serde_json::to_string(value).map_err(|err| err.to_string()),
+            // `toml::to_string`/`from_str` require a map/struct at the
+            // document root, same as the `toml` crate everywhere else --
+            // callers whose `T` isn't itself a top-level map get a
+            // `FormatError` back here rather than a panic further up the
+            // pipeline.
+            Format::Toml => toml::to_string(value).map_err(|err| err.to_string()),
+            Format::Yaml => serde_yaml::to_string(value).map_err(|err| err.to_string()),
+        }
+    }
+}
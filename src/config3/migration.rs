@@ -0,0 +1,89 @@
+use serde_json::Value;
+
+/// A single step in a config schema's upgrade path.
+///
+/// `run` receives the raw document at version `from` and must return it
+/// rewritten to version `to`. [`super::ConfigurationFile`] applies every
+/// registered step in sequence, starting from whatever `version` field the
+/// on-disk document reports, until it reaches the newest version any
+/// registered step upgrades to.
+#[derive(Clone, Copy)]
+pub struct Migration {
+    pub from: u32,
+    pub to: u32,
+    pub run: fn(Value) -> Result<Value, String>,
+}
+
+/// What went wrong while bringing a document up to the current schema
+/// version.
+pub(super) enum MigrationError {
+    /// The document's `version` is newer than any migration step knows
+    /// about — this crate has no idea how to read it.
+    UnsupportedVersion(u32),
+    /// A migration step failed, or no step exists to advance past the
+    /// document's current version.
+    Failed(String),
+}
+
+pub(super) enum MigrationOutcome {
+    /// The document was already at the newest known version.
+    UpToDate(Value),
+    /// The document was rewritten and should be persisted back to disk.
+    Migrated(Value),
+}
+
+fn read_version(value: &Value) -> u32 {
+    value.get("version").and_then(Value::as_u64).unwrap_or(0) as u32
+}
+
+/// Writes `version` into `value`'s `version` field, so the re-rendered
+/// document reflects the version a [`Migration::run`] step upgraded it to
+/// rather than relying on every step to set it themselves.
+fn write_version(value: &mut Value, version: u32) {
+    if let Value::Object(map) = value {
+        map.insert("version".to_string(), Value::from(version));
+    }
+}
+
+/// Runs `value` through `migrations` until it reaches the newest version
+/// any step upgrades to. A no-op (returning `UpToDate`) if `migrations` is
+/// empty, so documents with no `version` field are unaffected.
+pub(super) fn apply_migrations(
+    mut value: Value,
+    migrations: &[Migration],
+) -> Result<MigrationOutcome, MigrationError> {
+    if migrations.is_empty() {
+        return Ok(MigrationOutcome::UpToDate(value));
+    }
+
+    let target = migrations.iter().map(|m| m.to).max().unwrap_or(0);
+    let mut version = read_version(&value);
+
+    if version > target {
+        return Err(MigrationError::UnsupportedVersion(version));
+    }
+
+    let mut migrated = false;
+    while version < target {
+        let step = migrations
+            .iter()
+            .find(|m| m.from == version)
+            .ok_or_else(|| {
+                MigrationError::Failed(format!("no migration from version {version}"))
+            })?;
+
+        value = (step.run)(value).map_err(MigrationError::Failed)?;
+        version = step.to;
+        // Stamped here rather than left to `step.run`, so a forgetful
+        // migration author can't silently leave the on-disk `version` stale
+        // -- which would make the next run reapply a step that already ran.
+        write_version(&mut value, version);
+        migrated = true;
+    }
+
+    Ok(if migrated {
+        MigrationOutcome::Migrated(value)
+    } else {
+        MigrationOutcome::UpToDate(value)
+    })
+}
@@ -0,0 +1,13 @@
+mod builder;
+mod config_file;
+mod file_handler;
+mod format;
+mod migration;
+mod remote;
+
+pub use builder::ConfigurationFileBuilder;
+pub use config_file::{ConfigResult, ConfigurationFile};
+pub use file_handler::FileHander;
+pub use format::{ConfigFormat, Format, FormatError};
+pub use migration::Migration;
+pub use remote::{ConfigClient, ConfigServer};
@@ -0,0 +1,294 @@
+use std::{
+    marker::PhantomData,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use color_eyre::{eyre::eyre, Result};
+use serde::{de::DeserializeOwned, Serialize};
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+    sync::watch,
+    task::JoinHandle,
+};
+
+use super::config_file::ConfigResult;
+
+/// Frames are length-prefixed `serde_json`: a `u32` big-endian byte count
+/// followed by that many bytes encoding a single `T`. Only `T` ever crosses
+/// the wire (not the full `ConfigResult<T>`) -- a [`ConfigServer`] only ever
+/// pushes *valid* snapshots, and a remote edit is by definition a new `T`.
+///
+/// Every connection starts with one extra frame: the client sends its
+/// shared secret before anything else, and the server closes the
+/// connection without reading or writing any config data if it doesn't
+/// match. Encrypting the channel itself (mTLS or equivalent) is still left
+/// to the deployment layer -- e.g. terminating behind `stunnel` or a
+/// service-mesh sidecar -- since this module doesn't vendor a TLS stack,
+/// but authentication of the peer is not optional.
+async fn write_frame(
+    stream: &mut (impl AsyncWrite + Unpin),
+    payload: &[u8],
+) -> std::io::Result<()> {
+    stream
+        .write_all(&(payload.len() as u32).to_be_bytes())
+        .await?;
+    stream.write_all(payload).await
+}
+
+/// The largest frame [`read_frame`] will allocate a buffer for. The length
+/// prefix is attacker-controlled and read before any authentication
+/// succeeds (the handshake's own secret frame goes through the same
+/// function), so this has to guard the allocation itself, not just
+/// validated config payloads.
+const MAX_FRAME_LEN: u32 = 16 * 1024 * 1024;
+
+async fn read_frame(stream: &mut (impl AsyncRead + Unpin)) -> std::io::Result<Vec<u8>> {
+    let mut len = [0u8; 4];
+    stream.read_exact(&mut len).await?;
+    let len = u32::from_be_bytes(len);
+
+    if len > MAX_FRAME_LEN {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("frame of {len} bytes exceeds the {MAX_FRAME_LEN}-byte limit"),
+        ));
+    }
+
+    let mut payload = vec![0u8; len as usize];
+    stream.read_exact(&mut payload).await?;
+    Ok(payload)
+}
+
+/// Compares two byte strings in constant time, so checking the handshake
+/// secret doesn't leak how many leading bytes matched through its timing.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+
+    a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+/// Publishes a [`super::ConfigurationFile`]'s watch stream to remote
+/// subscribers, and applies their edits back through its `tx()`.
+pub struct ConfigServer<T> {
+    listener_thread: JoinHandle<()>,
+    _marker: PhantomData<T>,
+}
+
+impl<T: Default + Serialize + DeserializeOwned + Send + Sync + 'static> ConfigServer<T> {
+    /// Binds `addr` and, for every connecting client that presents
+    /// `shared_secret` as its first frame, pushes new valid snapshots from
+    /// `rx` and applies frames it reads back through `tx`. Connections that
+    /// don't authenticate are dropped before any config data is read or
+    /// sent.
+    pub async fn bind(
+        addr: impl ToSocketAddrs,
+        shared_secret: impl Into<String>,
+        rx: watch::Receiver<ConfigResult<T>>,
+        tx: watch::Sender<ConfigResult<T>>,
+    ) -> Result<Self> {
+        let listener = TcpListener::bind(addr).await?;
+        let shared_secret = Arc::new(shared_secret.into());
+
+        let listener_thread = tokio::spawn(async move {
+            loop {
+                let (stream, peer) = match listener.accept().await {
+                    Ok(pair) => pair,
+                    Err(err) => {
+                        eprintln!("Failed to accept remote config subscriber: {err}");
+                        continue;
+                    }
+                };
+
+                println!("Remote config subscriber connected: {peer}");
+                tokio::spawn(Self::serve_client(
+                    stream,
+                    shared_secret.clone(),
+                    rx.clone(),
+                    tx.clone(),
+                ));
+            }
+        });
+
+        Ok(Self {
+            listener_thread,
+            _marker: PhantomData,
+        })
+    }
+
+    async fn serve_client(
+        stream: TcpStream,
+        shared_secret: Arc<String>,
+        mut rx: watch::Receiver<ConfigResult<T>>,
+        tx: watch::Sender<ConfigResult<T>>,
+    ) {
+        let (mut read_half, mut write_half) = stream.into_split();
+
+        match read_frame(&mut read_half).await {
+            Ok(token) if constant_time_eq(&token, shared_secret.as_bytes()) => {}
+            _ => {
+                eprintln!("Rejecting remote config subscriber: invalid or missing auth token");
+                return;
+            }
+        }
+
+        let push = async move {
+            loop {
+                // Encoded before awaiting, so the `watch::Ref` (not `Send`)
+                // is released before the write.
+                let payload = match &*rx.borrow_and_update() {
+                    ConfigResult::Valid(value) => Some(
+                        serde_json::to_vec(value)
+                            .expect("Internal config object should always be valid."),
+                    ),
+                    _ => None,
+                };
+
+                if let Some(payload) = payload {
+                    if write_frame(&mut write_half, &payload).await.is_err() {
+                        break;
+                    }
+                }
+
+                if rx.changed().await.is_err() {
+                    break;
+                }
+            }
+        };
+
+        let pull = async move {
+            loop {
+                let payload = match read_frame(&mut read_half).await {
+                    Ok(payload) => payload,
+                    Err(_) => break,
+                };
+
+                match serde_json::from_slice::<T>(&payload) {
+                    Ok(value) => tx.send_modify(|current| *current = value.into()),
+                    Err(err) => eprintln!("Discarding malformed remote edit: {err}"),
+                }
+            }
+        };
+
+        tokio::join!(push, pull);
+    }
+
+    pub fn stop(self) {
+        self.listener_thread.abort();
+    }
+}
+
+/// Connects to a [`ConfigServer`] and presents the same `rx()`/`tx()` API as
+/// a local [`super::ConfigurationFile`], so consuming code doesn't need to
+/// care whether its config is local or remote.
+pub struct ConfigClient<T> {
+    reader_thread: JoinHandle<()>,
+    writer_thread: JoinHandle<()>,
+    rx: watch::Receiver<ConfigResult<T>>,
+    tx: watch::Sender<ConfigResult<T>>,
+}
+
+impl<T: Default + Serialize + DeserializeOwned + PartialEq + Clone + Send + Sync + 'static>
+    ConfigClient<T>
+{
+    /// How long [`Self::connect`] waits for the server's first snapshot
+    /// before giving up.
+    const DEFAULT_SNAPSHOT_TIMEOUT: Duration = Duration::from_secs(10);
+
+    /// Connects to a [`ConfigServer`], authenticating with `shared_secret`.
+    pub async fn connect(addr: impl ToSocketAddrs, shared_secret: impl AsRef<str>) -> Result<Self> {
+        Self::connect_timeout(addr, shared_secret, Self::DEFAULT_SNAPSHOT_TIMEOUT).await
+    }
+
+    /// Like [`Self::connect`], but with a custom bound on how long to wait
+    /// for the server's first snapshot. A [`ConfigServer`] only pushes once
+    /// its underlying config is `Valid` (see [`ConfigServer::serve_client`]),
+    /// so a server stuck on an invalid or unsupported-version config would
+    /// otherwise hang this forever.
+    pub async fn connect_timeout(
+        addr: impl ToSocketAddrs,
+        shared_secret: impl AsRef<str>,
+        timeout: Duration,
+    ) -> Result<Self> {
+        let stream = TcpStream::connect(addr).await?;
+        let (mut read_half, mut write_half) = stream.into_split();
+
+        write_frame(&mut write_half, shared_secret.as_ref().as_bytes()).await?;
+
+        let first = tokio::time::timeout(timeout, read_frame(&mut read_half))
+            .await
+            .map_err(|_| eyre!("timed out waiting for the server's first config snapshot"))??;
+        let initial: T = serde_json::from_slice(&first)?;
+
+        // Holds the decoded value of the last snapshot the reader applied,
+        // so the writer can tell a genuine local edit apart from its own
+        // reflection bouncing back through `config_rx`. Tagging *the value
+        // itself* (rather than a bare "a push just landed" flag) is load-
+        // bearing: `watch` coalesces wakeups, so a local edit arriving
+        // between the reader's `send_replace` and the writer next being
+        // polled would otherwise be discarded under a stale flag meant for
+        // the server's push, not this one.
+        let last_from_server: Arc<Mutex<Option<T>>> = Arc::new(Mutex::new(Some(initial.clone())));
+
+        let (config_tx, mut config_rx) = watch::channel(ConfigResult::from(initial));
+        config_rx.mark_changed();
+
+        let config_tx2 = config_tx.clone();
+        let last_from_server2 = last_from_server.clone();
+        let reader_thread = tokio::spawn(async move {
+            while let Ok(payload) = read_frame(&mut read_half).await {
+                match serde_json::from_slice::<T>(&payload) {
+                    Ok(value) => {
+                        *last_from_server2.lock().unwrap() = Some(value.clone());
+                        config_tx2.send_replace(value.into());
+                    }
+                    Err(err) => eprintln!("Discarding malformed config push: {err}"),
+                }
+            }
+        });
+
+        let mut config_rx2 = config_rx.clone();
+        let writer_thread = tokio::spawn(async move {
+            while config_rx2.changed().await.is_ok() {
+                // Encoded before awaiting, so the `watch::Ref` (not `Send`)
+                // is released before the write.
+                let payload = match &*config_rx2.borrow_and_update() {
+                    ConfigResult::Valid(value)
+                        if last_from_server.lock().unwrap().as_ref() != Some(value) =>
+                    {
+                        serde_json::to_vec(value)
+                            .expect("Internal config object should always be valid.")
+                    }
+                    _ => continue,
+                };
+
+                if write_frame(&mut write_half, &payload).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(Self {
+            reader_thread,
+            writer_thread,
+            rx: config_rx,
+            tx: config_tx,
+        })
+    }
+
+    pub fn rx(&self) -> watch::Receiver<ConfigResult<T>> {
+        self.rx.clone()
+    }
+
+    pub fn tx(&self) -> watch::Sender<ConfigResult<T>> {
+        self.tx.clone()
+    }
+
+    pub fn stop(self) {
+        self.reader_thread.abort();
+        self.writer_thread.abort();
+    }
+}